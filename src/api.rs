@@ -3,20 +3,33 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+use crate::auth::{self, AuthenticatedNode};
+use crate::federation;
 use crate::models::{Event, MasterServer, Node};
+use crate::ws;
+
+/// Default page size for `GET /events` when the caller doesn't specify `limit`.
+const DEFAULT_EVENTS_LIMIT: usize = 500;
 
 /// Request for retrieving events since a timestamp
 #[derive(Deserialize)]
 struct EventsRequest {
     since: DateTime<Utc>,
+    limit: Option<usize>,
 }
 
 /// Handler for POST /event endpoint
 async fn post_event(
+    auth: AuthenticatedNode,
     event: web::Json<Event>,
     server: web::Data<Arc<MasterServer>>,
 ) -> impl Responder {
-    match server.log_event(event.into_inner()) {
+    let event = event.into_inner();
+    if event.origin_id != auth.node_id {
+        return HttpResponse::Unauthorized().body("origin_id does not match authenticated node");
+    }
+
+    match server.log_event(event) {
         Ok(_) => HttpResponse::Created().finish(),
         Err(e) => HttpResponse::BadRequest().body(e),
     }
@@ -27,16 +40,30 @@ async fn get_events(
     query: web::Query<EventsRequest>,
     server: web::Data<Arc<MasterServer>>,
 ) -> impl Responder {
-    let events = server.get_events_since(query.since);
+    let limit = query.limit.unwrap_or(DEFAULT_EVENTS_LIMIT);
+    let events = server.get_events_since(query.since, None, limit);
     HttpResponse::Ok().json(events)
 }
 
 /// Handler for POST /node endpoint
 async fn register_node(
+    auth: AuthenticatedNode,
     node: web::Json<Node>,
     server: web::Data<Arc<MasterServer>>,
 ) -> impl Responder {
-    server.register_node(node.into_inner());
+    let mut node = node.into_inner();
+    if node.node_id != auth.node_id {
+        return HttpResponse::Unauthorized().body("node_id does not match authenticated node");
+    }
+
+    // verify_keys/old_verify_keys may only change via rotate_key, so this
+    // client-supplied copy is discarded in favor of whatever's on file
+    // (empty, for a node registering for the first time).
+    let on_file = server.get_node(&node.node_id);
+    node.verify_keys = on_file.as_ref().map_or_else(Vec::new, |n| n.verify_keys.clone());
+    node.old_verify_keys = on_file.map_or_else(Vec::new, |n| n.old_verify_keys);
+
+    server.register_node(node);
     HttpResponse::Created().finish()
 }
 
@@ -46,6 +73,31 @@ async fn get_nodes(server: web::Data<Arc<MasterServer>>) -> impl Responder {
     HttpResponse::Ok().json(nodes)
 }
 
+/// Request body for `POST /node/{node_id}/rotate-key`.
+#[derive(Deserialize)]
+struct RotateKeyRequest {
+    new_pubkey: String,
+}
+
+/// Handler for POST /node/{node_id}/rotate-key endpoint: a node rotates its
+/// own signing key, retiring the old one into `old_verify_keys`.
+async fn rotate_node_key(
+    auth: AuthenticatedNode,
+    path: web::Path<String>,
+    req: web::Json<RotateKeyRequest>,
+    server: web::Data<Arc<MasterServer>>,
+) -> impl Responder {
+    let node_id = path.into_inner();
+    if node_id != auth.node_id {
+        return HttpResponse::Unauthorized().body("can only rotate your own node's key");
+    }
+
+    match server.rotate_node_key(&node_id, req.into_inner().new_pubkey) {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(e) => HttpResponse::BadRequest().body(e),
+    }
+}
+
 /// Configure the API routes
 pub fn configure_app(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -63,5 +115,29 @@ pub fn configure_app(cfg: &mut web::ServiceConfig) {
     .service(
         web::resource("/nodes")
             .route(web::get().to(get_nodes))
+    )
+    .service(
+        web::resource("/node/{node_id}/rotate-key")
+            .route(web::post().to(rotate_node_key))
+    )
+    .service(
+        web::resource("/ws")
+            .route(web::get().to(ws::ws_index))
+    )
+    .service(
+        web::resource("/register")
+            .route(web::post().to(auth::register))
+    )
+    .service(
+        web::resource("/login")
+            .route(web::post().to(auth::login))
+    )
+    .service(
+        web::resource("/federation/transaction")
+            .route(web::post().to(federation::receive_transaction))
+    )
+    .service(
+        web::resource("/federation/keys")
+            .route(web::get().to(federation::get_keys))
     );
 }
\ No newline at end of file
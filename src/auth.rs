@@ -0,0 +1,208 @@
+use actix_web::dev::Payload;
+use actix_web::error::ErrorUnauthorized;
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse, Responder};
+use chrono::{Duration, Utc};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use std::future::{ready, Ready};
+use std::sync::Arc;
+
+use crate::models::MasterServer;
+
+/// How long an issued token stays valid.
+const TOKEN_TTL_SECONDS: i64 = 3600;
+
+#[derive(Serialize, Deserialize)]
+struct Claims {
+    node_id: String,
+    exp: usize,
+}
+
+/// Request body for `POST /register`.
+#[derive(Deserialize)]
+struct RegisterRequest {
+    node_id: String,
+    password: String,
+}
+
+/// Request body for `POST /login`.
+#[derive(Deserialize)]
+struct LoginRequest {
+    node_id: String,
+    password: String,
+}
+
+#[derive(Serialize)]
+struct LoginResponse {
+    token: String,
+}
+
+/// Handler for `POST /register`: stores a bcrypt-hashed password for a node id.
+pub async fn register(
+    req: web::Json<RegisterRequest>,
+    server: web::Data<Arc<MasterServer>>,
+) -> impl Responder {
+    match server.register_credential(&req.node_id, &req.password) {
+        Ok(_) => HttpResponse::Created().finish(),
+        Err(e) => HttpResponse::BadRequest().body(e),
+    }
+}
+
+/// Handler for `POST /login`: checks the password and, if it matches, issues a
+/// signed JWT carrying `node_id` and an expiry.
+pub async fn login(
+    req: web::Json<LoginRequest>,
+    server: web::Data<Arc<MasterServer>>,
+) -> impl Responder {
+    match server.verify_credential(&req.node_id, &req.password) {
+        Ok(true) => {
+            let claims = Claims {
+                node_id: req.node_id.clone(),
+                exp: (Utc::now() + Duration::seconds(TOKEN_TTL_SECONDS)).timestamp() as usize,
+            };
+            let token = encode(
+                &Header::new(Algorithm::HS256),
+                &claims,
+                &EncodingKey::from_secret(server.jwt_secret().as_bytes()),
+            );
+            match token {
+                Ok(token) => HttpResponse::Ok().json(LoginResponse { token }),
+                Err(_) => HttpResponse::InternalServerError().finish(),
+            }
+        }
+        Ok(false) => HttpResponse::Unauthorized().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(e),
+    }
+}
+
+/// Extractor proving the request carried a valid, unexpired bearer token for
+/// `node_id`. Use as a handler argument to gate a route on authentication.
+pub struct AuthenticatedNode {
+    pub node_id: String,
+}
+
+impl FromRequest for AuthenticatedNode {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let server = match req.app_data::<web::Data<Arc<MasterServer>>>() {
+            Some(server) => server.clone(),
+            None => return ready(Err(ErrorUnauthorized("server state unavailable"))),
+        };
+
+        let token = req
+            .headers()
+            .get("Authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        let token = match token {
+            Some(token) => token,
+            None => return ready(Err(ErrorUnauthorized("missing bearer token"))),
+        };
+
+        let claims = decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(server.jwt_secret().as_bytes()),
+            &Validation::new(Algorithm::HS256),
+        );
+
+        match claims {
+            Ok(data) => ready(Ok(AuthenticatedNode {
+                node_id: data.claims.node_id,
+            })),
+            Err(_) => ready(Err(ErrorUnauthorized("invalid or expired token"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{InMemoryCredentialStore, InMemoryEventStore, InMemoryNodeStore};
+    use actix_web::test::TestRequest;
+
+    fn test_server() -> web::Data<Arc<MasterServer>> {
+        web::Data::new(Arc::new(MasterServer::with_stores(
+            Box::new(InMemoryEventStore::new()),
+            Box::new(InMemoryNodeStore::new()),
+            Box::new(InMemoryCredentialStore::new()),
+        )))
+    }
+
+    #[actix_web::test]
+    async fn register_then_login_issues_a_token() {
+        let server = test_server();
+        let registered = register(
+            web::Json(RegisterRequest {
+                node_id: "node-1".to_string(),
+                password: "hunter2".to_string(),
+            }),
+            server.clone(),
+        )
+        .await
+        .respond_to(&TestRequest::default().to_http_request());
+        assert_eq!(registered.status(), actix_web::http::StatusCode::CREATED);
+
+        let logged_in = login(
+            web::Json(LoginRequest {
+                node_id: "node-1".to_string(),
+                password: "hunter2".to_string(),
+            }),
+            server,
+        )
+        .await
+        .respond_to(&TestRequest::default().to_http_request());
+        assert_eq!(logged_in.status(), actix_web::http::StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn register_rejects_an_already_registered_node_id() {
+        let server = test_server();
+        let register_request = || RegisterRequest {
+            node_id: "node-1".to_string(),
+            password: "hunter2".to_string(),
+        };
+        register(web::Json(register_request()), server.clone()).await;
+
+        let second = register(web::Json(register_request()), server)
+            .await
+            .respond_to(&TestRequest::default().to_http_request());
+        assert_eq!(second.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn login_rejects_the_wrong_password() {
+        let server = test_server();
+        register(
+            web::Json(RegisterRequest {
+                node_id: "node-1".to_string(),
+                password: "hunter2".to_string(),
+            }),
+            server.clone(),
+        )
+        .await;
+
+        let logged_in = login(
+            web::Json(LoginRequest {
+                node_id: "node-1".to_string(),
+                password: "wrong".to_string(),
+            }),
+            server,
+        )
+        .await
+        .respond_to(&TestRequest::default().to_http_request());
+        assert_eq!(logged_in.status(), actix_web::http::StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn authenticated_node_rejects_a_request_with_no_bearer_token() {
+        let server = test_server();
+        let req = TestRequest::default().app_data(server).to_http_request();
+        let mut payload = Payload::None;
+
+        assert!(AuthenticatedNode::from_request(&req, &mut payload)
+            .await
+            .is_err());
+    }
+}
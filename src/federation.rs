@@ -0,0 +1,219 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::models::{Event, MasterServer};
+
+/// How often the outbound component flushes queued events to peers.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+/// Send attempts per peer per flush, the first one included, before giving up
+/// on that batch.
+const MAX_RETRY_ATTEMPTS: u32 = 5;
+
+/// Body of `POST /federation/transaction`: a batch of events pushed by `origin_server`.
+#[derive(Serialize, Deserialize)]
+struct Transaction {
+    origin_server: String,
+    events: Vec<Event>,
+}
+
+/// The base URLs of servers we federate with, configured via `FEDERATION_PEERS`
+/// at startup and shared as app state. `receive_transaction` only trusts an
+/// inbound `origin_server` that appears here, rather than fetching a signing
+/// key from whatever URL the request body claims.
+#[derive(Clone, Default)]
+pub struct PeerAllowlist(Vec<String>);
+
+impl PeerAllowlist {
+    pub fn new(peers: Vec<String>) -> Self {
+        PeerAllowlist(peers)
+    }
+
+    fn allows(&self, origin_server: &str) -> bool {
+        self.0
+            .iter()
+            .any(|peer| peer.trim_end_matches('/') == origin_server.trim_end_matches('/'))
+    }
+}
+
+/// Handler for `GET /federation/keys`: publishes this server's Ed25519 signing
+/// key so peers can verify the `X-Signature` header on inbound transactions.
+pub async fn get_keys(server: web::Data<Arc<MasterServer>>) -> impl Responder {
+    HttpResponse::Ok().json(serde_json::json!({
+        "public_key": server.federation_public_key_hex(),
+    }))
+}
+
+/// Handler for `POST /federation/transaction`: verifies `origin_server` is a
+/// configured peer, verifies the request signature against the sender's
+/// published key, then verifies and idempotently inserts each event.
+pub async fn receive_transaction(
+    req: HttpRequest,
+    body: web::Bytes,
+    server: web::Data<Arc<MasterServer>>,
+    peers: web::Data<PeerAllowlist>,
+) -> impl Responder {
+    let signature_hex = match req
+        .headers()
+        .get("X-Signature")
+        .and_then(|value| value.to_str().ok())
+    {
+        Some(signature) => signature.to_string(),
+        None => return HttpResponse::Unauthorized().body("missing X-Signature header"),
+    };
+
+    let transaction: Transaction = match serde_json::from_slice(&body) {
+        Ok(transaction) => transaction,
+        Err(e) => return HttpResponse::BadRequest().body(format!("invalid transaction body: {}", e)),
+    };
+
+    if !peers.allows(&transaction.origin_server) {
+        return HttpResponse::Unauthorized()
+            .body("origin_server is not a configured federation peer");
+    }
+
+    let peer_public_key = match fetch_peer_public_key(&transaction.origin_server).await {
+        Ok(key) => key,
+        Err(e) => return HttpResponse::Unauthorized().body(format!("could not verify origin_server: {}", e)),
+    };
+
+    if let Err(e) = MasterServer::verify_federation_signature(&peer_public_key, &body, &signature_hex) {
+        return HttpResponse::Unauthorized().body(e);
+    }
+
+    let mut accepted = 0usize;
+    let mut rejected = Vec::new();
+    for event in transaction.events {
+        match server.log_event(event) {
+            Ok(_) => accepted += 1,
+            Err(e) => rejected.push(e),
+        }
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "accepted": accepted,
+        "rejected": rejected,
+    }))
+}
+
+async fn fetch_peer_public_key(origin_server: &str) -> Result<String, String> {
+    #[derive(Deserialize)]
+    struct KeysResponse {
+        public_key: String,
+    }
+
+    let url = format!("{}/federation/keys", origin_server.trim_end_matches('/'));
+    let response = reqwest::get(&url)
+        .await
+        .map_err(|e| format!("fetching {}: {}", url, e))?
+        .json::<KeysResponse>()
+        .await
+        .map_err(|e| format!("parsing keys response from {}: {}", url, e))?;
+    Ok(response.public_key)
+}
+
+/// Background task: every `FLUSH_INTERVAL`, drain events accepted since the last
+/// flush and push them as a signed transaction to every configured peer,
+/// retrying with exponential backoff on failure.
+pub async fn run_outbox(server: Arc<MasterServer>, base_url: String, peers: Vec<String>) {
+    let client = reqwest::Client::new();
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        interval.tick().await;
+        let events = server.drain_federation_outbox();
+        if events.is_empty() || peers.is_empty() {
+            continue;
+        }
+
+        let transaction = Transaction {
+            origin_server: base_url.clone(),
+            events,
+        };
+        let body = match serde_json::to_vec(&transaction) {
+            Ok(body) => body,
+            Err(_) => continue,
+        };
+        let signature = server.sign_federation_payload(&body);
+
+        for peer in &peers {
+            send_transaction_with_retry(&client, peer, &body, &signature).await;
+        }
+    }
+}
+
+async fn send_transaction_with_retry(client: &reqwest::Client, peer: &str, body: &[u8], signature: &str) {
+    let url = format!("{}/federation/transaction", peer.trim_end_matches('/'));
+    for attempt in 0..MAX_RETRY_ATTEMPTS {
+        let result = client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .header("X-Signature", signature)
+            .body(body.to_vec())
+            .send()
+            .await;
+
+        match result {
+            Ok(response) if response.status().is_success() => return,
+            _ => {
+                let backoff = Duration::from_secs(2u64.pow(attempt.min(6)));
+                tokio::time::sleep(backoff).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MasterServer;
+    use crate::storage::{InMemoryCredentialStore, InMemoryEventStore, InMemoryNodeStore};
+    use actix_web::test::TestRequest;
+
+    fn test_server() -> Arc<MasterServer> {
+        Arc::new(MasterServer::with_stores(
+            Box::new(InMemoryEventStore::new()),
+            Box::new(InMemoryNodeStore::new()),
+            Box::new(InMemoryCredentialStore::new()),
+        ))
+    }
+
+    #[test]
+    fn federation_signature_round_trips_and_rejects_tampering() {
+        let server = test_server();
+        let body = br#"{"origin_server":"https://a.example","events":[]}"#.to_vec();
+        let signature = server.sign_federation_payload(&body);
+        let public_key = server.federation_public_key_hex();
+
+        assert!(MasterServer::verify_federation_signature(&public_key, &body, &signature).is_ok());
+
+        let mut tampered = body.clone();
+        tampered.push(b'!');
+        assert!(
+            MasterServer::verify_federation_signature(&public_key, &tampered, &signature).is_err()
+        );
+    }
+
+    #[test]
+    fn peer_allowlist_matches_regardless_of_a_trailing_slash() {
+        let allowlist = PeerAllowlist::new(vec!["https://trusted.example".to_string()]);
+        assert!(allowlist.allows("https://trusted.example"));
+        assert!(allowlist.allows("https://trusted.example/"));
+        assert!(!allowlist.allows("https://evil.example"));
+    }
+
+    #[actix_web::test]
+    async fn receive_transaction_rejects_an_unconfigured_origin_server() {
+        let server = web::Data::new(test_server());
+        let peers = web::Data::new(PeerAllowlist::new(vec!["https://trusted.example".to_string()]));
+        let body = web::Bytes::from_static(br#"{"origin_server":"https://evil.example","events":[]}"#);
+        let req = TestRequest::default()
+            .insert_header(("X-Signature", "deadbeef"))
+            .to_http_request();
+
+        let response = receive_transaction(req, body, server, peers)
+            .await
+            .respond_to(&TestRequest::default().to_http_request());
+        assert_eq!(response.status(), actix_web::http::StatusCode::UNAUTHORIZED);
+    }
+}
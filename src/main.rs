@@ -1,16 +1,56 @@
 use actix_web::{App, HttpServer, middleware, web};
 use std::sync::Arc;
 
+mod auth;
 mod models;
 mod api;
+mod federation;
+mod storage;
+mod ws;
 
 use models::MasterServer;
 
+const DB_PATH: &str = "events.db";
+
+/// This server's own base URL, advertised as `origin_server` on outbound
+/// federation transactions and used by peers to fetch our signing key.
+const FEDERATION_BASE_URL_ENV: &str = "FEDERATION_BASE_URL";
+/// Comma-separated list of peer base URLs to federate with.
+const FEDERATION_PEERS_ENV: &str = "FEDERATION_PEERS";
+
+/// How often to sweep nodes whose current signing keys have all expired.
+const KEY_SWEEP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     // Initialize the server with thread-safe wrapping
-    let server = Arc::new(MasterServer::new());
-    
+    let server = Arc::new(
+        MasterServer::new(DB_PATH).unwrap_or_else(|e| panic!("failed to open {}: {}", DB_PATH, e)),
+    );
+
+    let peers: Vec<String> = std::env::var(FEDERATION_PEERS_ENV)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    if let Ok(base_url) = std::env::var(FEDERATION_BASE_URL_ENV) {
+        let federation_server = server.clone();
+        tokio::spawn(federation::run_outbox(federation_server, base_url, peers.clone()));
+    }
+    let peer_allowlist = web::Data::new(federation::PeerAllowlist::new(peers));
+
+    let sweep_server = server.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(KEY_SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            sweep_server.sweep_expired_node_keys();
+        }
+    });
+
     // Start the HTTP server
     HttpServer::new(move || {
         App::new()
@@ -18,6 +58,7 @@ async fn main() -> std::io::Result<()> {
             .wrap(middleware::Logger::default())
             // Add shared state
             .app_data(web::Data::new(server.clone()))
+            .app_data(peer_allowlist.clone())
             // Configure routes
             .configure(api::configure_app)
     })
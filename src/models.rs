@@ -1,7 +1,16 @@
 use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature as Ed25519Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use secp256k1::schnorr::Signature;
+use secp256k1::{Message, Secp256k1, XOnlyPublicKey};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use crate::storage::{CredentialStore, EventStore, NodeStore};
 
 /// Represents an event in the system
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -20,6 +29,10 @@ pub struct Node {
     pub node_id: String,          // Unique identifier for the node
     pub last_active: DateTime<Utc>, // Last active timestamp
     pub status: NodeStatus,       // Current status of the node
+    #[serde(default)]
+    pub verify_keys: Vec<VerifyKey>, // Signing keys currently valid for this node
+    #[serde(default)]
+    pub old_verify_keys: Vec<VerifyKey>, // Retired keys, kept to verify historical events
 }
 
 /// Enum for node status
@@ -29,104 +42,358 @@ pub enum NodeStatus {
     Inactive,
 }
 
-/// Event log that maintains authenticated events
+/// A node's signing public key (hex, x-only secp256k1) together with the time
+/// window during which events signed by it are accepted. `valid_until: None`
+/// means the key is still the node's current active key.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VerifyKey {
+    pub pubkey: String,
+    pub valid_from: DateTime<Utc>,
+    pub valid_until: Option<DateTime<Utc>>,
+}
+
+impl VerifyKey {
+    /// Whether this key was the valid signing key at `timestamp`.
+    fn covers(&self, timestamp: DateTime<Utc>) -> bool {
+        timestamp >= self.valid_from && self.valid_until.map_or(true, |until| timestamp < until)
+    }
+}
+
+/// Event log that maintains authenticated events, persisted through an `EventStore`.
 pub struct EventLog {
-    events: Arc<Mutex<Vec<Event>>>,
+    store: Box<dyn EventStore>,
+    node_registry: Arc<NodeRegistry>,
 }
 
 impl EventLog {
-    pub fn new() -> Self {
-        EventLog {
-            events: Arc::new(Mutex::new(Vec::new())),
-        }
+    pub fn new(store: Box<dyn EventStore>, node_registry: Arc<NodeRegistry>) -> Self {
+        EventLog { store, node_registry }
     }
 
     /// Add a new event to the log after verifying its signature
     pub fn add_event(&self, event: Event) -> Result<(), String> {
-        if !self.verify_signature(&event) {
-            return Err("Invalid event signature".to_string());
-        }
-        
-        let mut events = self.events.lock().unwrap();
-        events.push(event);
-        Ok(())
+        self.verify_signature(&event)?;
+        self.store.insert_event(&event)
     }
 
-    /// Retrieve events since a specific timestamp
-    pub fn get_events_since(&self, timestamp: DateTime<Utc>) -> Vec<Event> {
-        let events = self.events.lock().unwrap();
-        events.iter()
-            .filter(|e| e.timestamp > timestamp)
-            .cloned()
-            .collect()
+    /// Retrieve events ordered by `(timestamp, event_id)` since `(timestamp,
+    /// since_event_id)`, capped at `limit` rows. Pass the `event_id` of the last
+    /// row seen as `since_event_id` to page forward without skipping events
+    /// that share a timestamp with it; see `EventStore::events_since`.
+    pub fn get_events_since(
+        &self,
+        timestamp: DateTime<Utc>,
+        since_event_id: Option<&str>,
+        limit: usize,
+    ) -> Vec<Event> {
+        self.store.events_since(timestamp, since_event_id, limit)
     }
 
-    /// Verify the digital signature of an event
-    fn verify_signature(&self, event: &Event) -> bool {
-        // Placeholder for actual signature verification logic
-        // In a real implementation, this would use cryptographic libraries
-        true
+    /// Verify the digital signature of an event.
+    ///
+    /// `origin_id` identifies the `Node` that produced the event; the actual
+    /// signing key is resolved via `NodeRegistry::verify_key_for`, which looks at
+    /// the key that was valid for that node at `event.timestamp` (including
+    /// rotated-out keys retained in `old_verify_keys`). `signature` is a 64-byte
+    /// BIP340 Schnorr signature (hex), following Nostr's event id / signing
+    /// scheme: the event id is the SHA-256 digest of the canonical JSON array
+    /// `[0, origin_id, timestamp, event_type, payload]`, and the signature is a
+    /// Schnorr signature over that digest.
+    fn verify_signature(&self, event: &Event) -> Result<(), String> {
+        let digest = canonical_event_digest(event);
+        let expected_id = hex::encode(digest);
+        if expected_id != event.event_id {
+            return Err(format!(
+                "event id mismatch: computed {} but event declares {}",
+                expected_id, event.event_id
+            ));
+        }
+
+        let pubkey_hex = self
+            .node_registry
+            .verify_key_for(&event.origin_id, event.timestamp)
+            .ok_or_else(|| {
+                format!(
+                    "no verify key valid for node {} at event timestamp",
+                    event.origin_id
+                )
+            })?;
+
+        let pubkey_bytes =
+            hex::decode(&pubkey_hex).map_err(|e| format!("invalid verify key hex: {}", e))?;
+        let pubkey = XOnlyPublicKey::from_slice(&pubkey_bytes)
+            .map_err(|e| format!("invalid verify key: {}", e))?;
+
+        let sig_bytes =
+            hex::decode(&event.signature).map_err(|e| format!("invalid signature hex: {}", e))?;
+        let signature = Signature::from_slice(&sig_bytes)
+            .map_err(|e| format!("malformed schnorr signature: {}", e))?;
+
+        let message =
+            Message::from_slice(&digest).map_err(|e| format!("invalid digest: {}", e))?;
+        let secp = Secp256k1::verification_only();
+        secp.verify_schnorr(&signature, &message, &pubkey)
+            .map_err(|_| "schnorr signature verification failed".to_string())
     }
 }
 
-/// Registry for tracking nodes in the network
+/// Compute the canonical event id digest: SHA-256 of the compact JSON array
+/// `[0, origin_id, timestamp_unix_secs, event_type, payload]`.
+pub(crate) fn canonical_event_digest(event: &Event) -> [u8; 32] {
+    let serialized = serde_json::to_string(&serde_json::json!([
+        0,
+        event.origin_id,
+        event.timestamp.timestamp(),
+        event.event_type,
+        event.payload,
+    ]))
+    .expect("array of serializable fields cannot fail to serialize");
+
+    let mut hasher = Sha256::new();
+    hasher.update(serialized.as_bytes());
+    hasher.finalize().into()
+}
+
+/// Registry for tracking nodes in the network, persisted through a `NodeStore`.
 pub struct NodeRegistry {
-    nodes: Arc<Mutex<HashMap<String, Node>>>,
+    store: Box<dyn NodeStore>,
 }
 
 impl NodeRegistry {
-    pub fn new() -> Self {
-        NodeRegistry {
-            nodes: Arc::new(Mutex::new(HashMap::new())),
-        }
+    pub fn new(store: Box<dyn NodeStore>) -> Self {
+        NodeRegistry { store }
     }
 
     /// Register or update a node
     pub fn register_node(&self, node: Node) {
-        let mut nodes = self.nodes.lock().unwrap();
-        nodes.insert(node.node_id.clone(), node);
+        let _ = self.store.upsert_node(&node);
     }
 
     /// Get a list of all active nodes
     pub fn get_active_nodes(&self) -> Vec<Node> {
-        let nodes = self.nodes.lock().unwrap();
-        nodes.values()
-            .filter(|n| n.status == NodeStatus::Active)
-            .cloned()
-            .collect()
+        self.store.active_nodes()
+    }
+
+    /// Look up a single node by id, regardless of status.
+    pub fn get_node(&self, node_id: &str) -> Option<Node> {
+        self.store.get_node(node_id)
     }
 
     /// Mark a node as inactive
     pub fn mark_node_inactive(&self, node_id: &str) {
-        let mut nodes = self.nodes.lock().unwrap();
-        if let Some(node) = nodes.get_mut(node_id) {
-            node.status = NodeStatus::Inactive;
+        let _ = self.store.mark_inactive(node_id);
+    }
+
+    /// The hex-encoded signing pubkey that was valid for `node_id` at `timestamp`,
+    /// checking the node's current `verify_keys` first and then its
+    /// `old_verify_keys`. Returns `None` if the node is unknown or had no key
+    /// covering that moment.
+    pub fn verify_key_for(&self, node_id: &str, timestamp: DateTime<Utc>) -> Option<String> {
+        let node = self.store.get_node(node_id)?;
+        node.verify_keys
+            .iter()
+            .chain(node.old_verify_keys.iter())
+            .find(|key| key.covers(timestamp))
+            .map(|key| key.pubkey.clone())
+    }
+
+    /// Retire a node's current signing key(s) into `old_verify_keys` and make
+    /// `new_pubkey` the active key going forward. Events signed with the
+    /// retired key before this moment remain verifiable.
+    pub fn rotate_key(&self, node_id: &str, new_pubkey: String) -> Result<(), String> {
+        let mut node = self
+            .store
+            .get_node(node_id)
+            .ok_or_else(|| format!("unknown node {}", node_id))?;
+
+        let now = Utc::now();
+        for key in node.verify_keys.iter_mut() {
+            if key.valid_until.is_none() {
+                key.valid_until = Some(now);
+            }
+        }
+        node.old_verify_keys.append(&mut node.verify_keys);
+        node.verify_keys = vec![VerifyKey {
+            pubkey: new_pubkey,
+            valid_from: now,
+            valid_until: None,
+        }];
+
+        self.store.upsert_node(&node)
+    }
+
+    /// Mark every active node Inactive once none of its current `verify_keys`
+    /// cover the present moment, so a node with only expired keys can't keep
+    /// being treated as reachable.
+    pub fn sweep_expired_keys(&self) {
+        let now = Utc::now();
+        for node in self.store.active_nodes() {
+            let has_active_key = node.verify_keys.iter().any(|key| key.covers(now));
+            if !has_active_key {
+                let _ = self.store.mark_inactive(&node.node_id);
+            }
         }
     }
 }
 
+/// Capacity of the live event broadcast channel; slow subscribers that fall this
+/// far behind the tip simply miss the oldest buffered events rather than blocking.
+const EVENT_BROADCAST_CAPACITY: usize = 1024;
+
 /// The main server structure that manages the event log and node registry
 pub struct MasterServer {
     event_log: EventLog,
-    node_registry: NodeRegistry,
+    node_registry: Arc<NodeRegistry>,
+    credential_store: Box<dyn CredentialStore>,
+    jwt_secret: String,
+    event_tx: broadcast::Sender<Event>,
+    federation_signing_key: SigningKey,
+    federation_outbox: Mutex<VecDeque<Event>>,
 }
 
 impl MasterServer {
-    pub fn new() -> Self {
+    /// Build a server backed by a SQLite database at `db_path`.
+    pub fn new(db_path: &str) -> Result<Self, String> {
+        let event_store = crate::storage::SqliteEventStore::open(db_path)?;
+        let node_store = crate::storage::SqliteNodeStore::open(db_path)?;
+        let credential_store = crate::storage::SqliteCredentialStore::open(db_path)?;
+        Ok(Self::with_stores(
+            Box::new(event_store),
+            Box::new(node_store),
+            Box::new(credential_store),
+        ))
+    }
+
+    /// Build a server from explicit stores, e.g. the in-memory implementations
+    /// tests use instead of talking to SQLite. The JWT signing secret is read
+    /// back from `credential_store` if one was already persisted there (so a
+    /// restart doesn't invalidate every outstanding token); otherwise a fresh
+    /// secret is generated and persisted for next time.
+    pub fn with_stores(
+        event_store: Box<dyn EventStore>,
+        node_store: Box<dyn NodeStore>,
+        credential_store: Box<dyn CredentialStore>,
+    ) -> Self {
+        let (event_tx, _) = broadcast::channel(EVENT_BROADCAST_CAPACITY);
+        let jwt_secret = match credential_store.jwt_secret() {
+            Some(secret) => secret,
+            None => {
+                let mut secret_bytes = [0u8; 32];
+                rand::thread_rng().fill_bytes(&mut secret_bytes);
+                let secret = hex::encode(secret_bytes);
+                let _ = credential_store.set_jwt_secret(&secret);
+                secret
+            }
+        };
+        let node_registry = Arc::new(NodeRegistry::new(node_store));
         MasterServer {
-            event_log: EventLog::new(),
-            node_registry: NodeRegistry::new(),
+            event_log: EventLog::new(event_store, node_registry.clone()),
+            node_registry,
+            credential_store,
+            jwt_secret,
+            event_tx,
+            federation_signing_key: SigningKey::generate(&mut OsRng),
+            federation_outbox: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// The server's HS256 JWT signing secret, used to both issue and validate
+    /// bearer tokens for `/login`-authenticated requests.
+    pub fn jwt_secret(&self) -> &str {
+        &self.jwt_secret
+    }
+
+    /// API endpoint: Register a node credential with a bcrypt-hashed password.
+    /// Rejects `node_id`s that are already registered so one node can't
+    /// silently take over another's credential (and, by extension, its JWT
+    /// identity) by re-registering it.
+    pub fn register_credential(&self, node_id: &str, password: &str) -> Result<(), String> {
+        if self.credential_store.password_hash(node_id).is_some() {
+            return Err(format!("node {} is already registered", node_id));
         }
+        let password_hash = bcrypt::hash(password, bcrypt::DEFAULT_COST)
+            .map_err(|e| format!("hashing password: {}", e))?;
+        self.credential_store.set_password_hash(node_id, &password_hash)
+    }
+
+    /// API endpoint: Check a node's password against its stored credential.
+    pub fn verify_credential(&self, node_id: &str, password: &str) -> Result<bool, String> {
+        let password_hash = match self.credential_store.password_hash(node_id) {
+            Some(hash) => hash,
+            None => return Ok(false),
+        };
+        bcrypt::verify(password, &password_hash).map_err(|e| format!("verifying password: {}", e))
     }
 
     /// API endpoint: Log a new event
     pub fn log_event(&self, event: Event) -> Result<(), String> {
-        self.event_log.add_event(event)
+        self.event_log.add_event(event.clone())?;
+        // No active subscribers is not an error; the event is still stored.
+        let _ = self.event_tx.send(event.clone());
+        self.federation_outbox.lock().unwrap().push_back(event);
+        Ok(())
+    }
+
+    /// This server's Ed25519 federation signing key, hex-encoded, as published
+    /// at `GET /federation/keys` for peers to verify inbound transactions.
+    pub fn federation_public_key_hex(&self) -> String {
+        hex::encode(self.federation_signing_key.verifying_key().to_bytes())
+    }
+
+    /// Sign a federation transaction body with this server's Ed25519 key,
+    /// returning the hex-encoded signature to send as `X-Signature`.
+    pub fn sign_federation_payload(&self, body: &[u8]) -> String {
+        hex::encode(self.federation_signing_key.sign(body).to_bytes())
     }
 
-    /// API endpoint: Retrieve events since a specific timestamp
-    pub fn get_events_since(&self, timestamp: DateTime<Utc>) -> Vec<Event> {
-        self.event_log.get_events_since(timestamp)
+    /// Verify a federation transaction body against a peer's published Ed25519
+    /// public key and hex-encoded signature.
+    pub fn verify_federation_signature(
+        peer_public_key_hex: &str,
+        body: &[u8],
+        signature_hex: &str,
+    ) -> Result<(), String> {
+        let key_bytes = hex::decode(peer_public_key_hex)
+            .map_err(|e| format!("invalid peer public key hex: {}", e))?;
+        let key_bytes: [u8; 32] = key_bytes
+            .try_into()
+            .map_err(|_| "peer public key must be 32 bytes".to_string())?;
+        let verifying_key =
+            VerifyingKey::from_bytes(&key_bytes).map_err(|e| format!("invalid peer public key: {}", e))?;
+
+        let sig_bytes =
+            hex::decode(signature_hex).map_err(|e| format!("invalid signature hex: {}", e))?;
+        let sig_bytes: [u8; 64] = sig_bytes
+            .try_into()
+            .map_err(|_| "signature must be 64 bytes".to_string())?;
+        let signature = Ed25519Signature::from_bytes(&sig_bytes);
+
+        verifying_key
+            .verify(body, &signature)
+            .map_err(|_| "federation transaction signature verification failed".to_string())
+    }
+
+    /// Drain every event accepted since the last flush, for the federation
+    /// outbound component to batch into a transaction.
+    pub fn drain_federation_outbox(&self) -> Vec<Event> {
+        let mut outbox = self.federation_outbox.lock().unwrap();
+        outbox.drain(..).collect()
+    }
+
+    /// Subscribe to the live stream of events accepted by `log_event`.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.event_tx.subscribe()
+    }
+
+    /// API endpoint: Retrieve events ordered by `(timestamp, event_id)` since
+    /// `(timestamp, since_event_id)`, capped at `limit` rows.
+    pub fn get_events_since(
+        &self,
+        timestamp: DateTime<Utc>,
+        since_event_id: Option<&str>,
+        limit: usize,
+    ) -> Vec<Event> {
+        self.event_log.get_events_since(timestamp, since_event_id, limit)
     }
 
     /// API endpoint: Register a node
@@ -138,4 +405,167 @@ impl MasterServer {
     pub fn get_active_nodes(&self) -> Vec<Node> {
         self.node_registry.get_active_nodes()
     }
+
+    /// API endpoint: Look up a single node by id, regardless of status.
+    pub fn get_node(&self, node_id: &str) -> Option<Node> {
+        self.node_registry.get_node(node_id)
+    }
+
+    /// Rotate a node's signing key, retiring its current key(s) into
+    /// `old_verify_keys` so past events stay verifiable.
+    pub fn rotate_node_key(&self, node_id: &str, new_pubkey: String) -> Result<(), String> {
+        self.node_registry.rotate_key(node_id, new_pubkey)
+    }
+
+    /// Mark nodes Inactive once all of their current signing keys have expired.
+    /// Intended to be called periodically from a background task.
+    pub fn sweep_expired_node_keys(&self) {
+        self.node_registry.sweep_expired_keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{InMemoryEventStore, InMemoryNodeStore};
+    use secp256k1::KeyPair;
+
+    /// Register a node whose current verify key is `pubkey_hex`, valid since
+    /// `valid_from`, and return an `EventLog` backed by that registry.
+    fn event_log_with_node(node_id: &str, pubkey_hex: &str, valid_from: DateTime<Utc>) -> EventLog {
+        let node_registry = Arc::new(NodeRegistry::new(Box::new(InMemoryNodeStore::new())));
+        node_registry.register_node(Node {
+            node_id: node_id.to_string(),
+            last_active: Utc::now(),
+            status: NodeStatus::Active,
+            verify_keys: vec![VerifyKey {
+                pubkey: pubkey_hex.to_string(),
+                valid_from,
+                valid_until: None,
+            }],
+            old_verify_keys: Vec::new(),
+        });
+        EventLog::new(Box::new(InMemoryEventStore::new()), node_registry)
+    }
+
+    /// Build and sign a valid event from `node_id`, signed with `keypair`.
+    fn signed_event(
+        secp: &Secp256k1<secp256k1::All>,
+        keypair: &KeyPair,
+        node_id: &str,
+        event_type: &str,
+        payload: serde_json::Value,
+    ) -> Event {
+        let mut event = Event {
+            event_id: String::new(),
+            timestamp: Utc::now(),
+            origin_id: node_id.to_string(),
+            event_type: event_type.to_string(),
+            payload,
+            signature: String::new(),
+        };
+
+        let digest = canonical_event_digest(&event);
+        event.event_id = hex::encode(digest);
+        let message = Message::from_slice(&digest).expect("digest is 32 bytes");
+        let signature = secp.sign_schnorr(&message, keypair);
+        event.signature = signature.to_string();
+        event
+    }
+
+    fn pubkey_hex(keypair: &KeyPair) -> String {
+        let (pubkey, _parity) = keypair.x_only_public_key();
+        hex::encode(pubkey.serialize())
+    }
+
+    #[test]
+    fn add_event_accepts_validly_signed_event() {
+        let secp = Secp256k1::new();
+        let keypair = KeyPair::new(&secp, &mut rand::thread_rng());
+        let event = signed_event(&secp, &keypair, "node-1", "join", serde_json::json!({"hello": "world"}));
+
+        let log = event_log_with_node("node-1", &pubkey_hex(&keypair), event.timestamp - chrono::Duration::seconds(1));
+        assert!(log.add_event(event).is_ok());
+    }
+
+    #[test]
+    fn add_event_rejects_tampered_payload() {
+        let secp = Secp256k1::new();
+        let keypair = KeyPair::new(&secp, &mut rand::thread_rng());
+        let mut event = signed_event(&secp, &keypair, "node-1", "data_update", serde_json::json!({"n": 1}));
+        let log = event_log_with_node("node-1", &pubkey_hex(&keypair), event.timestamp - chrono::Duration::seconds(1));
+        event.payload = serde_json::json!({"n": 2});
+
+        assert!(log.add_event(event).is_err());
+    }
+
+    #[test]
+    fn add_event_rejects_signature_from_wrong_key() {
+        let secp = Secp256k1::new();
+        let keypair = KeyPair::new(&secp, &mut rand::thread_rng());
+        let other_keypair = KeyPair::new(&secp, &mut rand::thread_rng());
+        // Signed with `other_keypair` but claiming to be "node-1", whose
+        // registered verify key is `keypair`'s pubkey.
+        let event = signed_event(&secp, &other_keypair, "node-1", "leave", serde_json::json!({}));
+
+        let log = event_log_with_node("node-1", &pubkey_hex(&keypair), event.timestamp - chrono::Duration::seconds(1));
+        assert!(log.add_event(event).is_err());
+    }
+
+    #[test]
+    fn add_event_rejects_malformed_hex() {
+        let secp = Secp256k1::new();
+        let keypair = KeyPair::new(&secp, &mut rand::thread_rng());
+        let mut event = signed_event(&secp, &keypair, "node-1", "join", serde_json::json!({}));
+        event.signature = "not-hex".to_string();
+
+        let log = event_log_with_node("node-1", &pubkey_hex(&keypair), event.timestamp - chrono::Duration::seconds(1));
+        assert!(log.add_event(event).is_err());
+    }
+
+    #[test]
+    fn add_event_rejects_event_outside_key_validity_window() {
+        let secp = Secp256k1::new();
+        let keypair = KeyPair::new(&secp, &mut rand::thread_rng());
+        let event = signed_event(&secp, &keypair, "node-1", "join", serde_json::json!({}));
+
+        // Key only becomes valid after the event's timestamp.
+        let log = event_log_with_node("node-1", &pubkey_hex(&keypair), event.timestamp + chrono::Duration::seconds(60));
+        assert!(log.add_event(event).is_err());
+    }
+
+    #[test]
+    fn rotate_key_preserves_verification_of_past_events_and_accepts_new_key() {
+        let secp = Secp256k1::new();
+        let old_keypair = KeyPair::new(&secp, &mut rand::thread_rng());
+        let new_keypair = KeyPair::new(&secp, &mut rand::thread_rng());
+
+        let old_event = signed_event(&secp, &old_keypair, "node-1", "join", serde_json::json!({}));
+        let node_registry = Arc::new(NodeRegistry::new(Box::new(InMemoryNodeStore::new())));
+        node_registry.register_node(Node {
+            node_id: "node-1".to_string(),
+            last_active: Utc::now(),
+            status: NodeStatus::Active,
+            verify_keys: vec![VerifyKey {
+                pubkey: pubkey_hex(&old_keypair),
+                valid_from: old_event.timestamp - chrono::Duration::seconds(1),
+                valid_until: None,
+            }],
+            old_verify_keys: Vec::new(),
+        });
+        let log = EventLog::new(Box::new(InMemoryEventStore::new()), node_registry.clone());
+
+        assert!(log.add_event(old_event).is_ok());
+
+        node_registry
+            .rotate_key("node-1", pubkey_hex(&new_keypair))
+            .unwrap();
+
+        let new_event = signed_event(&secp, &new_keypair, "node-1", "join", serde_json::json!({}));
+        assert!(log.add_event(new_event).is_ok());
+
+        // A new event signed with the retired key after rotation is rejected.
+        let replay_with_old_key = signed_event(&secp, &old_keypair, "node-1", "join", serde_json::json!({"n": 2}));
+        assert!(log.add_event(replay_with_old_key).is_err());
+    }
 }
\ No newline at end of file
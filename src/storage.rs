@@ -0,0 +1,607 @@
+use chrono::{DateTime, TimeZone, Utc};
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::models::{Event, Node, NodeStatus};
+
+/// Persistence for the event log, selectable at `MasterServer::with_stores` so tests can
+/// swap in `InMemoryEventStore` instead of talking to a real database.
+pub trait EventStore: Send + Sync {
+    /// Insert an already signature-verified event. Inserting an `event_id` that's
+    /// already present must be a no-op rather than an error, so replayed events
+    /// (e.g. from federation or client retries) are deduped silently.
+    fn insert_event(&self, event: &Event) -> Result<(), String>;
+
+    /// Events ordered by `(timestamp, event_id)`, strictly after the cursor
+    /// `(since, since_event_id)`, capped at `limit` rows. When `since_event_id`
+    /// is `None`, this is a plain `timestamp > since`. When it's `Some`, ties at
+    /// exactly `since` are broken by `event_id`, so a caller paging forward by
+    /// re-querying with the last row's `(timestamp, event_id)` can't silently
+    /// skip other events sharing that same whole-second timestamp.
+    fn events_since(
+        &self,
+        since: DateTime<Utc>,
+        since_event_id: Option<&str>,
+        limit: usize,
+    ) -> Vec<Event>;
+}
+
+/// Persistence for the node registry, selectable at `MasterServer::with_stores` alongside
+/// `EventStore`.
+pub trait NodeStore: Send + Sync {
+    /// Insert or update the node's row, keyed on `node_id`.
+    fn upsert_node(&self, node: &Node) -> Result<(), String>;
+
+    fn mark_inactive(&self, node_id: &str) -> Result<(), String>;
+
+    fn active_nodes(&self) -> Vec<Node>;
+
+    /// Look up a single node by id, regardless of status.
+    fn get_node(&self, node_id: &str) -> Option<Node>;
+}
+
+/// Persistence for node login credentials, selectable at `MasterServer::with_stores`
+/// alongside `EventStore`/`NodeStore`. Only ever stores a bcrypt hash, never a
+/// plaintext password.
+pub trait CredentialStore: Send + Sync {
+    /// Insert or replace the stored password hash for `node_id`.
+    fn set_password_hash(&self, node_id: &str, password_hash: &str) -> Result<(), String>;
+
+    /// The stored bcrypt hash for `node_id`, if a credential has been registered.
+    fn password_hash(&self, node_id: &str) -> Option<String>;
+
+    /// The server's persisted JWT signing secret, if one has been generated yet.
+    fn jwt_secret(&self) -> Option<String>;
+
+    /// Persist the JWT signing secret so it survives a server restart.
+    fn set_jwt_secret(&self, secret: &str) -> Result<(), String>;
+}
+
+/// In-memory `EventStore`, equivalent to the original `Vec<Event>`-backed behavior.
+/// Used by tests and anywhere a real database isn't wanted.
+#[derive(Default)]
+pub struct InMemoryEventStore {
+    events: Mutex<Vec<Event>>,
+}
+
+impl InMemoryEventStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl EventStore for InMemoryEventStore {
+    fn insert_event(&self, event: &Event) -> Result<(), String> {
+        let mut events = self.events.lock().unwrap();
+        if events.iter().any(|e| e.event_id == event.event_id) {
+            return Ok(());
+        }
+        events.push(event.clone());
+        Ok(())
+    }
+
+    fn events_since(
+        &self,
+        since: DateTime<Utc>,
+        since_event_id: Option<&str>,
+        limit: usize,
+    ) -> Vec<Event> {
+        let events = self.events.lock().unwrap();
+        let mut matching: Vec<Event> = events
+            .iter()
+            .filter(|e| is_after_cursor(e, since, since_event_id))
+            .cloned()
+            .collect();
+        matching.sort_by(|a, b| (a.timestamp, &a.event_id).cmp(&(b.timestamp, &b.event_id)));
+        matching.truncate(limit);
+        matching
+    }
+}
+
+/// Whether `event` comes strictly after the `(since, since_event_id)` cursor,
+/// under `(timestamp, event_id)` ordering. See `EventStore::events_since`.
+fn is_after_cursor(event: &Event, since: DateTime<Utc>, since_event_id: Option<&str>) -> bool {
+    match since_event_id {
+        Some(after_id) => {
+            event.timestamp > since || (event.timestamp == since && event.event_id.as_str() > after_id)
+        }
+        None => event.timestamp > since,
+    }
+}
+
+/// In-memory `NodeStore`, equivalent to the original `HashMap<String, Node>`-backed
+/// behavior.
+#[derive(Default)]
+pub struct InMemoryNodeStore {
+    nodes: Mutex<HashMap<String, Node>>,
+}
+
+impl InMemoryNodeStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl NodeStore for InMemoryNodeStore {
+    fn upsert_node(&self, node: &Node) -> Result<(), String> {
+        let mut nodes = self.nodes.lock().unwrap();
+        nodes.insert(node.node_id.clone(), node.clone());
+        Ok(())
+    }
+
+    fn mark_inactive(&self, node_id: &str) -> Result<(), String> {
+        let mut nodes = self.nodes.lock().unwrap();
+        if let Some(node) = nodes.get_mut(node_id) {
+            node.status = NodeStatus::Inactive;
+        }
+        Ok(())
+    }
+
+    fn active_nodes(&self) -> Vec<Node> {
+        let nodes = self.nodes.lock().unwrap();
+        nodes
+            .values()
+            .filter(|n| n.status == NodeStatus::Active)
+            .cloned()
+            .collect()
+    }
+
+    fn get_node(&self, node_id: &str) -> Option<Node> {
+        let nodes = self.nodes.lock().unwrap();
+        nodes.get(node_id).cloned()
+    }
+}
+
+/// In-memory `CredentialStore`, keyed the same way `InMemoryNodeStore` is.
+#[derive(Default)]
+pub struct InMemoryCredentialStore {
+    password_hashes: Mutex<HashMap<String, String>>,
+    jwt_secret: Mutex<Option<String>>,
+}
+
+impl InMemoryCredentialStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CredentialStore for InMemoryCredentialStore {
+    fn set_password_hash(&self, node_id: &str, password_hash: &str) -> Result<(), String> {
+        let mut hashes = self.password_hashes.lock().unwrap();
+        hashes.insert(node_id.to_string(), password_hash.to_string());
+        Ok(())
+    }
+
+    fn password_hash(&self, node_id: &str) -> Option<String> {
+        let hashes = self.password_hashes.lock().unwrap();
+        hashes.get(node_id).cloned()
+    }
+
+    fn jwt_secret(&self) -> Option<String> {
+        self.jwt_secret.lock().unwrap().clone()
+    }
+
+    fn set_jwt_secret(&self, secret: &str) -> Result<(), String> {
+        *self.jwt_secret.lock().unwrap() = Some(secret.to_string());
+        Ok(())
+    }
+}
+
+/// SQLite-backed `EventStore`. `rusqlite::Connection` isn't `Sync`, so access is
+/// serialized behind a `Mutex` the same way the in-memory stores serialize theirs.
+pub struct SqliteEventStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteEventStore {
+    pub fn open(db_path: &str) -> Result<Self, String> {
+        let conn = Connection::open(db_path).map_err(|e| format!("opening event store: {}", e))?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS events (
+                event_id   TEXT PRIMARY KEY,
+                timestamp  INTEGER NOT NULL,
+                event_type TEXT NOT NULL,
+                origin_id  TEXT NOT NULL,
+                payload    TEXT NOT NULL,
+                signature  TEXT NOT NULL
+            )",
+            [],
+        )
+        .map_err(|e| format!("creating events table: {}", e))?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_events_timestamp ON events (timestamp)",
+            [],
+        )
+        .map_err(|e| format!("creating events timestamp index: {}", e))?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_events_event_type ON events (event_type)",
+            [],
+        )
+        .map_err(|e| format!("creating events event_type index: {}", e))?;
+
+        Ok(SqliteEventStore {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+impl EventStore for SqliteEventStore {
+    fn insert_event(&self, event: &Event) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR IGNORE INTO events (event_id, timestamp, event_type, origin_id, payload, signature)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                event.event_id,
+                event.timestamp.timestamp(),
+                event.event_type,
+                event.origin_id,
+                event.payload.to_string(),
+                event.signature,
+            ],
+        )
+        .map_err(|e| format!("inserting event: {}", e))?;
+        Ok(())
+    }
+
+    fn events_since(
+        &self,
+        since: DateTime<Utc>,
+        since_event_id: Option<&str>,
+        limit: usize,
+    ) -> Vec<Event> {
+        let conn = self.conn.lock().unwrap();
+        // `?2 IS NOT NULL AND ...` makes the tie-break clause a no-op when no
+        // since_event_id is given, so behavior without a cursor is unchanged.
+        let mut stmt = match conn.prepare(
+            "SELECT event_id, timestamp, event_type, origin_id, payload, signature
+             FROM events
+             WHERE timestamp > ?1 OR (?2 IS NOT NULL AND timestamp = ?1 AND event_id > ?2)
+             ORDER BY timestamp ASC, event_id ASC LIMIT ?3",
+        ) {
+            Ok(stmt) => stmt,
+            Err(_) => return Vec::new(),
+        };
+
+        let rows = stmt.query_map(params![since.timestamp(), since_event_id, limit as i64], |row| {
+            let timestamp_secs: i64 = row.get(1)?;
+            let payload_json: String = row.get(4)?;
+            Ok(Event {
+                event_id: row.get(0)?,
+                timestamp: Utc.timestamp_opt(timestamp_secs, 0).single().unwrap_or(Utc::now()),
+                event_type: row.get(2)?,
+                origin_id: row.get(3)?,
+                payload: serde_json::from_str(&payload_json).unwrap_or(serde_json::Value::Null),
+                signature: row.get(5)?,
+            })
+        });
+
+        match rows {
+            Ok(rows) => rows.filter_map(Result::ok).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+/// SQLite-backed `NodeStore`.
+pub struct SqliteNodeStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteNodeStore {
+    pub fn open(db_path: &str) -> Result<Self, String> {
+        let conn = Connection::open(db_path).map_err(|e| format!("opening node store: {}", e))?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS nodes (
+                node_id         TEXT PRIMARY KEY,
+                last_active     INTEGER NOT NULL,
+                status          TEXT NOT NULL,
+                verify_keys     TEXT NOT NULL DEFAULT '[]',
+                old_verify_keys TEXT NOT NULL DEFAULT '[]'
+            )",
+            [],
+        )
+        .map_err(|e| format!("creating nodes table: {}", e))?;
+
+        Ok(SqliteNodeStore {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+/// Build a `Node` from a `nodes` row: `(node_id, last_active, status, verify_keys, old_verify_keys)`.
+fn node_from_row(row: &rusqlite::Row) -> rusqlite::Result<Node> {
+    let last_active_secs: i64 = row.get(1)?;
+    let status: String = row.get(2)?;
+    let verify_keys_json: String = row.get(3)?;
+    let old_verify_keys_json: String = row.get(4)?;
+    Ok(Node {
+        node_id: row.get(0)?,
+        last_active: Utc
+            .timestamp_opt(last_active_secs, 0)
+            .single()
+            .unwrap_or(Utc::now()),
+        status: if status == "active" {
+            NodeStatus::Active
+        } else {
+            NodeStatus::Inactive
+        },
+        verify_keys: serde_json::from_str(&verify_keys_json).unwrap_or_default(),
+        old_verify_keys: serde_json::from_str(&old_verify_keys_json).unwrap_or_default(),
+    })
+}
+
+impl NodeStore for SqliteNodeStore {
+    fn upsert_node(&self, node: &Node) -> Result<(), String> {
+        let status = match node.status {
+            NodeStatus::Active => "active",
+            NodeStatus::Inactive => "inactive",
+        };
+        let verify_keys_json = serde_json::to_string(&node.verify_keys)
+            .map_err(|e| format!("serializing verify_keys: {}", e))?;
+        let old_verify_keys_json = serde_json::to_string(&node.old_verify_keys)
+            .map_err(|e| format!("serializing old_verify_keys: {}", e))?;
+
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO nodes (node_id, last_active, status, verify_keys, old_verify_keys)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(node_id) DO UPDATE SET
+                 last_active = excluded.last_active,
+                 status = excluded.status,
+                 verify_keys = excluded.verify_keys,
+                 old_verify_keys = excluded.old_verify_keys",
+            params![
+                node.node_id,
+                node.last_active.timestamp(),
+                status,
+                verify_keys_json,
+                old_verify_keys_json,
+            ],
+        )
+        .map_err(|e| format!("upserting node: {}", e))?;
+        Ok(())
+    }
+
+    fn mark_inactive(&self, node_id: &str) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE nodes SET status = 'inactive' WHERE node_id = ?1",
+            params![node_id],
+        )
+        .map_err(|e| format!("marking node inactive: {}", e))?;
+        Ok(())
+    }
+
+    fn active_nodes(&self) -> Vec<Node> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = match conn.prepare(
+            "SELECT node_id, last_active, status, verify_keys, old_verify_keys
+             FROM nodes WHERE status = 'active'",
+        ) {
+            Ok(stmt) => stmt,
+            Err(_) => return Vec::new(),
+        };
+
+        let rows = stmt.query_map([], node_from_row);
+        match rows {
+            Ok(rows) => rows.filter_map(Result::ok).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn get_node(&self, node_id: &str) -> Option<Node> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT node_id, last_active, status, verify_keys, old_verify_keys
+             FROM nodes WHERE node_id = ?1",
+            params![node_id],
+            node_from_row,
+        )
+        .ok()
+    }
+}
+
+/// SQLite-backed `CredentialStore`.
+pub struct SqliteCredentialStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteCredentialStore {
+    pub fn open(db_path: &str) -> Result<Self, String> {
+        let conn =
+            Connection::open(db_path).map_err(|e| format!("opening credential store: {}", e))?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS credentials (
+                node_id       TEXT PRIMARY KEY,
+                password_hash TEXT NOT NULL
+            )",
+            [],
+        )
+        .map_err(|e| format!("creating credentials table: {}", e))?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS server_secrets (
+                key   TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            )",
+            [],
+        )
+        .map_err(|e| format!("creating server_secrets table: {}", e))?;
+
+        Ok(SqliteCredentialStore {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+/// Key under which the JWT signing secret is stored in `server_secrets`.
+const JWT_SECRET_KEY: &str = "jwt_secret";
+
+impl CredentialStore for SqliteCredentialStore {
+    fn set_password_hash(&self, node_id: &str, password_hash: &str) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO credentials (node_id, password_hash) VALUES (?1, ?2)
+             ON CONFLICT(node_id) DO UPDATE SET password_hash = excluded.password_hash",
+            params![node_id, password_hash],
+        )
+        .map_err(|e| format!("storing credential: {}", e))?;
+        Ok(())
+    }
+
+    fn password_hash(&self, node_id: &str) -> Option<String> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT password_hash FROM credentials WHERE node_id = ?1",
+            params![node_id],
+            |row| row.get(0),
+        )
+        .ok()
+    }
+
+    fn jwt_secret(&self) -> Option<String> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT value FROM server_secrets WHERE key = ?1",
+            params![JWT_SECRET_KEY],
+            |row| row.get(0),
+        )
+        .ok()
+    }
+
+    fn set_jwt_secret(&self, secret: &str) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO server_secrets (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![JWT_SECRET_KEY, secret],
+        )
+        .map_err(|e| format!("storing jwt secret: {}", e))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(node_id: &str, status: NodeStatus) -> Node {
+        Node {
+            node_id: node_id.to_string(),
+            last_active: Utc::now(),
+            status,
+            verify_keys: Vec::new(),
+            old_verify_keys: Vec::new(),
+        }
+    }
+
+    fn event(event_id: &str, timestamp: DateTime<Utc>) -> Event {
+        Event {
+            event_id: event_id.to_string(),
+            timestamp,
+            origin_id: "node-1".to_string(),
+            event_type: "join".to_string(),
+            payload: serde_json::json!({}),
+            signature: "deadbeef".to_string(),
+        }
+    }
+
+    #[test]
+    fn insert_event_dedupes_replayed_event_ids() {
+        let store = InMemoryEventStore::new();
+        let now = Utc::now();
+        let mut replayed = event("event-1", now);
+
+        store.insert_event(&event("event-1", now)).unwrap();
+        replayed.payload = serde_json::json!({"tampered": true});
+        store.insert_event(&replayed).unwrap();
+
+        let events = store.events_since(now - chrono::Duration::seconds(1), None, 10);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].payload, serde_json::json!({}));
+    }
+
+    #[test]
+    fn events_since_excludes_events_at_or_before_the_cursor() {
+        let store = InMemoryEventStore::new();
+        let base = Utc::now();
+        store.insert_event(&event("event-1", base)).unwrap();
+        store
+            .insert_event(&event("event-2", base + chrono::Duration::seconds(1)))
+            .unwrap();
+
+        let events = store.events_since(base, None, 10);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_id, "event-2");
+    }
+
+    #[test]
+    fn events_since_with_a_cursor_breaks_ties_on_event_id_instead_of_dropping_them() {
+        let store = InMemoryEventStore::new();
+        let tied = Utc::now();
+        // More events than fit in one page, all sharing one exact timestamp.
+        let page_size = 3;
+        for n in 0..(page_size * 2 + 1) {
+            store
+                .insert_event(&event(&format!("event-{:02}", n), tied))
+                .unwrap();
+        }
+
+        let first_page = store.events_since(tied - chrono::Duration::seconds(1), None, page_size);
+        assert_eq!(first_page.len(), page_size);
+
+        let last = first_page.last().unwrap();
+        let second_page = store.events_since(last.timestamp, Some(last.event_id.as_str()), page_size);
+
+        // Without the event_id tie-break, re-querying `events_since(tied, None, ..)`
+        // would see `timestamp > tied` as false for every remaining event and
+        // silently report the backlog as exhausted.
+        assert_eq!(second_page.len(), page_size);
+        let seen: std::collections::HashSet<&str> = first_page
+            .iter()
+            .chain(second_page.iter())
+            .map(|e| e.event_id.as_str())
+            .collect();
+        assert_eq!(seen.len(), page_size * 2);
+    }
+
+    #[test]
+    fn upsert_node_replaces_the_existing_row() {
+        let store = InMemoryNodeStore::new();
+        store.upsert_node(&node("node-1", NodeStatus::Active)).unwrap();
+        store.upsert_node(&node("node-1", NodeStatus::Inactive)).unwrap();
+
+        let stored = store.get_node("node-1").unwrap();
+        assert_eq!(stored.status, NodeStatus::Inactive);
+        assert_eq!(store.active_nodes().len(), 0);
+    }
+
+    #[test]
+    fn active_nodes_excludes_inactive_nodes() {
+        let store = InMemoryNodeStore::new();
+        store.upsert_node(&node("node-1", NodeStatus::Active)).unwrap();
+        store.upsert_node(&node("node-2", NodeStatus::Inactive)).unwrap();
+
+        let active: Vec<String> = store.active_nodes().into_iter().map(|n| n.node_id).collect();
+        assert_eq!(active, vec!["node-1".to_string()]);
+    }
+
+    #[test]
+    fn set_password_hash_overwrites_the_existing_hash() {
+        let store = InMemoryCredentialStore::new();
+        store.set_password_hash("node-1", "hash-a").unwrap();
+        store.set_password_hash("node-1", "hash-b").unwrap();
+
+        assert_eq!(store.password_hash("node-1"), Some("hash-b".to_string()));
+    }
+
+    #[test]
+    fn jwt_secret_is_unset_until_stored() {
+        let store = InMemoryCredentialStore::new();
+        assert_eq!(store.jwt_secret(), None);
+
+        store.set_jwt_secret("topsecret").unwrap();
+        assert_eq!(store.jwt_secret(), Some("topsecret".to_string()));
+    }
+}
@@ -0,0 +1,353 @@
+use actix::{Actor, ActorContext, AsyncContext, StreamHandler};
+use actix_web::{web, Error, HttpRequest, HttpResponse};
+use actix_web_actors::ws;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+
+use crate::models::{Event, MasterServer};
+
+/// Page size for backlog queries to storage, and the cap on how many matching
+/// backlogged events a `REQ` without an explicit `limit` replays.
+const DEFAULT_BACKLOG_LIMIT: usize = 1000;
+
+/// Nostr-style filter accepted as the third element of a `["REQ", sub_id, filter]`
+/// message. All fields are optional; an event matches when it satisfies every
+/// field that is present.
+#[derive(Clone, Debug, Default, Deserialize)]
+struct Filter {
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    event_types: Option<Vec<String>>,
+    origin_ids: Option<Vec<String>>,
+    limit: Option<usize>,
+}
+
+impl Filter {
+    fn matches(&self, event: &Event) -> bool {
+        if let Some(since) = self.since {
+            if event.timestamp <= since {
+                return false;
+            }
+        }
+        if let Some(until) = self.until {
+            if event.timestamp > until {
+                return false;
+            }
+        }
+        if let Some(event_types) = &self.event_types {
+            if !event_types.contains(&event.event_type) {
+                return false;
+            }
+        }
+        if let Some(origin_ids) = &self.origin_ids {
+            if !origin_ids.contains(&event.origin_id) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A single `/ws` connection. Tracks the client's open subscriptions and relays
+/// matching events from the server's live broadcast stream until they're closed.
+pub struct EventsWs {
+    server: Arc<MasterServer>,
+    receiver: Option<broadcast::Receiver<Event>>,
+    subscriptions: HashMap<String, Filter>,
+}
+
+impl EventsWs {
+    pub fn new(server: Arc<MasterServer>) -> Self {
+        let receiver = server.subscribe();
+        EventsWs {
+            server,
+            receiver: Some(receiver),
+            subscriptions: HashMap::new(),
+        }
+    }
+
+    /// Handle a `["REQ", sub_id, filter]` message: replay the matching backlog,
+    /// send `EOSE`, then keep the filter active for the live stream.
+    fn handle_req(&mut self, ctx: &mut ws::WebsocketContext<Self>, sub_id: String, filter: Filter) {
+        for event in collect_backlog(&self.server, &filter) {
+            send_event(ctx, &sub_id, &event);
+        }
+        send_message(ctx, &serde_json::json!(["EOSE", sub_id]));
+
+        self.subscriptions.insert(sub_id, filter);
+    }
+
+    /// Handle a `["CLOSE", sub_id]` message: stop streaming events for that subscription.
+    fn handle_close(&mut self, sub_id: &str) {
+        self.subscriptions.remove(sub_id);
+    }
+
+    /// Parse and dispatch one incoming client message, which must be a
+    /// `["REQ", sub_id, filter]` or `["CLOSE", sub_id]` JSON array.
+    fn handle_client_message(&mut self, ctx: &mut ws::WebsocketContext<Self>, text: &str) {
+        let parsed: Value = match serde_json::from_str(text) {
+            Ok(value) => value,
+            Err(_) => return,
+        };
+        let parts = match parsed.as_array() {
+            Some(parts) if !parts.is_empty() => parts,
+            _ => return,
+        };
+
+        match parts[0].as_str() {
+            Some("REQ") => {
+                let (Some(sub_id), Some(filter_value)) = (parts.get(1), parts.get(2)) else {
+                    return;
+                };
+                let Some(sub_id) = sub_id.as_str() else {
+                    return;
+                };
+                let filter: Filter = match serde_json::from_value(filter_value.clone()) {
+                    Ok(filter) => filter,
+                    Err(_) => return,
+                };
+                self.handle_req(ctx, sub_id.to_string(), filter);
+            }
+            Some("CLOSE") => {
+                if let Some(sub_id) = parts.get(1).and_then(Value::as_str) {
+                    self.handle_close(sub_id);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Page forward through `server`'s backlog by `(timestamp, event_id)`, applying
+/// `filter` to each page, until enough matching events are collected or the
+/// store is exhausted. `get_events_since` only caps by the cursor/`limit`,
+/// with no way to filter by `event_types`/`origin_ids`/`until` at the store
+/// level, so a single page could be entirely non-matching for a narrow
+/// filter; kept free of `ctx` so the paging logic can be tested directly.
+fn collect_backlog(server: &MasterServer, filter: &Filter) -> Vec<Event> {
+    let target = filter.limit.unwrap_or(DEFAULT_BACKLOG_LIMIT);
+    let mut cursor = filter.since.unwrap_or(DateTime::<Utc>::MIN_UTC);
+    let mut cursor_event_id: Option<String> = None;
+    let mut backlog: Vec<Event> = Vec::new();
+    loop {
+        let page = server.get_events_since(cursor, cursor_event_id.as_deref(), DEFAULT_BACKLOG_LIMIT);
+        let page_len = page.len();
+        let Some(last) = page.last() else {
+            break;
+        };
+        cursor = last.timestamp;
+        cursor_event_id = Some(last.event_id.clone());
+        backlog.extend(page.into_iter().filter(|e| filter.matches(e)));
+
+        if backlog.len() >= target || page_len < DEFAULT_BACKLOG_LIMIT {
+            break;
+        }
+    }
+    backlog.truncate(target);
+    backlog
+}
+
+fn send_event(ctx: &mut ws::WebsocketContext<EventsWs>, sub_id: &str, event: &Event) {
+    send_message(ctx, &serde_json::json!(["EVENT", sub_id, event]));
+}
+
+fn send_message(ctx: &mut ws::WebsocketContext<EventsWs>, message: &Value) {
+    if let Ok(text) = serde_json::to_string(message) {
+        ctx.text(text);
+    }
+}
+
+impl Actor for EventsWs {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        if let Some(receiver) = self.receiver.take() {
+            ctx.add_stream(BroadcastStream::new(receiver));
+        }
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for EventsWs {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match msg {
+            Ok(ws::Message::Ping(msg)) => ctx.pong(&msg),
+            Ok(ws::Message::Text(text)) => self.handle_client_message(ctx, &text),
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl StreamHandler<Result<Event, tokio_stream::wrappers::errors::BroadcastStreamRecvError>>
+    for EventsWs
+{
+    fn handle(
+        &mut self,
+        event: Result<Event, tokio_stream::wrappers::errors::BroadcastStreamRecvError>,
+        ctx: &mut Self::Context,
+    ) {
+        // A lagged receiver (`Err`) just means some events were missed; nothing to relay.
+        let Ok(event) = event else {
+            return;
+        };
+        let matching_subs: Vec<String> = self
+            .subscriptions
+            .iter()
+            .filter(|(_, filter)| filter.matches(&event))
+            .map(|(sub_id, _)| sub_id.clone())
+            .collect();
+
+        for sub_id in matching_subs {
+            send_event(ctx, &sub_id, &event);
+        }
+    }
+}
+
+/// Handler for `GET /ws`: upgrades the connection and starts an `EventsWs` actor.
+pub async fn ws_index(
+    req: HttpRequest,
+    stream: web::Payload,
+    server: web::Data<Arc<MasterServer>>,
+) -> Result<HttpResponse, Error> {
+    ws::start(EventsWs::new(server.get_ref().clone()), &req, stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{canonical_event_digest, Node, NodeStatus, VerifyKey};
+    use crate::storage::{InMemoryCredentialStore, InMemoryEventStore, InMemoryNodeStore};
+    use chrono::TimeZone;
+    use secp256k1::{KeyPair, Message, Secp256k1};
+
+    fn event_at(timestamp: DateTime<Utc>, origin_id: &str, event_type: &str) -> Event {
+        Event {
+            event_id: "id".to_string(),
+            timestamp,
+            origin_id: origin_id.to_string(),
+            event_type: event_type.to_string(),
+            payload: serde_json::json!({}),
+            signature: "sig".to_string(),
+        }
+    }
+
+    #[test]
+    fn filter_matches_since_is_exclusive_and_until_is_inclusive() {
+        let base = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        let filter = Filter {
+            since: Some(base),
+            until: Some(base + chrono::Duration::seconds(10)),
+            ..Filter::default()
+        };
+
+        assert!(!filter.matches(&event_at(base, "node-1", "join")), "since is exclusive");
+        assert!(filter.matches(&event_at(base + chrono::Duration::seconds(1), "node-1", "join")));
+        assert!(
+            filter.matches(&event_at(base + chrono::Duration::seconds(10), "node-1", "join")),
+            "until is inclusive"
+        );
+        assert!(!filter.matches(&event_at(base + chrono::Duration::seconds(11), "node-1", "join")));
+    }
+
+    #[test]
+    fn filter_matches_event_types_and_origin_ids() {
+        let event = event_at(Utc::now(), "node-1", "join");
+
+        let filter = Filter {
+            event_types: Some(vec!["join".to_string()]),
+            ..Filter::default()
+        };
+        assert!(filter.matches(&event));
+
+        let filter = Filter {
+            event_types: Some(vec!["leave".to_string()]),
+            ..Filter::default()
+        };
+        assert!(!filter.matches(&event));
+
+        let filter = Filter {
+            origin_ids: Some(vec!["node-1".to_string()]),
+            ..Filter::default()
+        };
+        assert!(filter.matches(&event));
+
+        let filter = Filter {
+            origin_ids: Some(vec!["node-2".to_string()]),
+            ..Filter::default()
+        };
+        assert!(!filter.matches(&event));
+    }
+
+    /// Build and sign a valid event for `node_id` at `timestamp`.
+    fn signed_event(
+        secp: &Secp256k1<secp256k1::All>,
+        keypair: &KeyPair,
+        node_id: &str,
+        timestamp: DateTime<Utc>,
+        n: u32,
+    ) -> Event {
+        let mut event = Event {
+            event_id: String::new(),
+            timestamp,
+            origin_id: node_id.to_string(),
+            event_type: "join".to_string(),
+            payload: serde_json::json!({ "n": n }),
+            signature: String::new(),
+        };
+        let digest = canonical_event_digest(&event);
+        event.event_id = hex::encode(digest);
+        let message = Message::from_slice(&digest).expect("digest is 32 bytes");
+        event.signature = secp.sign_schnorr(&message, keypair).to_string();
+        event
+    }
+
+    #[test]
+    fn collect_backlog_pages_past_events_tied_at_one_timestamp() {
+        let secp = Secp256k1::new();
+        let keypair = KeyPair::new(&secp, &mut rand::thread_rng());
+        let (xonly, _) = keypair.x_only_public_key();
+
+        let tied_timestamp = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        let server = MasterServer::with_stores(
+            Box::new(InMemoryEventStore::new()),
+            Box::new(InMemoryNodeStore::new()),
+            Box::new(InMemoryCredentialStore::new()),
+        );
+        server.register_node(Node {
+            node_id: "node-1".to_string(),
+            last_active: Utc::now(),
+            status: NodeStatus::Active,
+            verify_keys: vec![VerifyKey {
+                pubkey: hex::encode(xonly.serialize()),
+                valid_from: tied_timestamp - chrono::Duration::seconds(1),
+                valid_until: None,
+            }],
+            old_verify_keys: Vec::new(),
+        });
+
+        let total_events = DEFAULT_BACKLOG_LIMIT + 5;
+        for n in 0..total_events {
+            let event = signed_event(&secp, &keypair, "node-1", tied_timestamp, n as u32);
+            server.log_event(event).unwrap();
+        }
+
+        let filter = Filter {
+            limit: Some(total_events),
+            ..Filter::default()
+        };
+        let backlog = collect_backlog(&server, &filter);
+        assert_eq!(
+            backlog.len(),
+            total_events,
+            "events tied at one timestamp must not be dropped across backlog pages"
+        );
+    }
+}